@@ -19,6 +19,8 @@ async fn main(_spawner: Spawner) {
     let config = Config {
         distance_unit: DistanceUnit::Centimeters,
         temperature_unit: TemperatureUnit::Celsius,
+        humidity: None,
+        max_distance: 400.0,
     };
 
     let mut sensor = Hcsr04::new(trigger, echo, config);
@@ -30,9 +32,12 @@ async fn main(_spawner: Spawner) {
     loop {
         let distance = sensor.measure(temperature).await;
         match distance {
-            Ok(distance) => {
+            Ok(Some(distance)) => {
                 info!("Distance: {} cm", distance);
             }
+            Ok(None) => {
+                info!("Out of range");
+            }
             Err(e) => {
                 info!("Error: {:?}", e);
             }
@@ -15,8 +15,13 @@
 use embassy_time::{with_timeout, Duration, Instant, Timer};
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::digital::Wait;
+use heapless::Vec;
 use libm::sqrt;
 
+/// The maximum number of samples `measure_filtered` will collect, bounding its fixed-capacity
+/// buffer without requiring an allocator.
+const MAX_FILTER_SAMPLES: usize = 16;
+
 /// The distance unit to use for measurements.
 pub enum DistanceUnit {
     Centimeters,
@@ -33,6 +38,69 @@ pub enum TemperatureUnit {
 pub struct Config {
     pub distance_unit: DistanceUnit,
     pub temperature_unit: TemperatureUnit,
+    /// The relative humidity, as a percentage (0-100), to use when compensating the speed of
+    /// sound for `measure`. Leave as `None` if unknown; `measure_with_humidity` can be used to
+    /// supply a one-off reading without changing this default.
+    pub humidity: Option<f64>,
+    /// The maximum distance, in the configured `distance_unit`, to listen for an echo. Readings
+    /// beyond this range are reported as `Ok(None)` instead of waiting out the sensor's full
+    /// 2-second timeout. The HC-SR04 itself is only reliable up to roughly 4 meters.
+    pub max_distance: f64,
+}
+
+/// A source of ambient temperature that can be queried asynchronously.
+///
+/// Implement this for a real thermometer (e.g. a BMP/DHT sensor) to let
+/// [`Hcsr04::measure_auto`] pull the current temperature automatically instead of
+/// requiring every caller to supply one.
+///
+/// `async_fn_in_trait` is allowed here: like `embedded_hal_async::digital::Wait`, this trait
+/// targets a single-executor embedded context where a `Send` bound on the returned future isn't
+/// needed.
+#[allow(async_fn_in_trait)]
+pub trait TemperatureProvider {
+    /// Returns the current ambient temperature, in the unit specified in the config.
+    async fn temperature(&mut self) -> f64;
+}
+
+/// A [`TemperatureProvider`] that always returns the same, fixed temperature.
+///
+/// Useful as a default when no real thermometer is available.
+pub struct ConstantTemperature(pub f64);
+
+impl TemperatureProvider for ConstantTemperature {
+    async fn temperature(&mut self) -> f64 {
+        self.0
+    }
+}
+
+/// Errors that can occur while taking a measurement.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Hcsr04Error {
+    /// The echo pin was already high when the measurement started.
+    EchoAlreadyHigh,
+    /// The echo pin did not go high within the expected window after the trigger pulse.
+    EchoTimeoutHigh,
+    /// The echo pin reported an error while waiting for it to go low.
+    EchoTimeoutLow,
+    /// The trigger pin returned an error.
+    TriggerPin,
+    /// The echo pin returned an error.
+    EchoPin,
+    /// `measure_filtered` did not obtain a single valid sample.
+    NoValidSamples,
+}
+
+/// How `measure_filtered` should combine multiple samples into a single reading.
+pub enum FilterStrategy {
+    /// Sort the valid samples and return the middle one, averaging the two middle samples when
+    /// the count is even.
+    Median,
+    /// Return the arithmetic mean of the valid samples.
+    Mean,
+    /// Return the smallest of the valid samples, i.e. the closest detected object.
+    MinValid,
 }
 
 /// The HC-SR04 ultrasonic distance sensor driver.
@@ -40,37 +108,159 @@ pub struct Config {
 /// # Note
 ///
 /// The `measure` method will return an error if the echo pin is already high.
-/// The `measure` method will return an error if the echo pin does not go high or low within 2 seconds each.
-pub struct Hcsr04<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait> {
+/// The `measure` method will return an error if the echo pin does not go high within 2 seconds.
+/// If the echo pin does not go low within the time an echo from `config.max_distance` away would
+/// take to return, the object is considered out of range and `measure` returns `Ok(None)` rather
+/// than an error.
+pub struct Hcsr04<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait, TP: TemperatureProvider = ConstantTemperature> {
     trigger: TRIGPIN,
     echo: ECHOPIN,
     config: Config,
+    temperature_provider: TP,
+    /// A per-sensor systematic bias, in the unit specified in the config, subtracted from every
+    /// computed distance. Set via `set_calibration_offset` or `calibrate_against`.
+    calibration_offset: f64,
+    /// The result and timestamp of the last successful `measure_cached` call, if any.
+    cached_reading: Option<(Option<f64>, Instant)>,
 }
 
-impl<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait> Hcsr04<TRIGPIN, ECHOPIN> {
+impl<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait> Hcsr04<TRIGPIN, ECHOPIN, ConstantTemperature> {
     /// Initialize a new sensor.
     /// Requires trigger pin and an echo pin, measurements are taken on the echo pin.
     /// Requires a config.
     pub fn new(trigger: TRIGPIN, echo: ECHOPIN, config: Config) -> Self {
+        // A room-temperature estimate, in the unit the caller configured, used as the
+        // ConstantTemperature provider that `measure_auto` queries. Use
+        // `with_temperature_provider` to build a sensor with a real provider instead.
+        let room_temperature = match config.temperature_unit {
+            TemperatureUnit::Celsius => 20.0,
+            TemperatureUnit::Fahrenheit => 68.0,
+        };
         Self {
             trigger,
             echo,
             config,
+            temperature_provider: ConstantTemperature(room_temperature),
+            calibration_offset: 0.0,
+            cached_reading: None,
         }
     }
+}
 
-    /// Calculate the speed of sound in meters per second, adjusted for temperature.
-    /// Takes a temperature in units specified in the config.
-    fn speed_of_sound_temperature_adjusted(&self, temperature: f64) -> f64 {
-        let temp = match self.config.temperature_unit {
-            TemperatureUnit::Celsius => {
-                temperature
-            },
-            TemperatureUnit::Fahrenheit => {
-                (temperature - 32.0) * 5.0 / 9.0
+impl<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait, TP: TemperatureProvider> Hcsr04<TRIGPIN, ECHOPIN, TP> {
+    /// Initialize a new sensor that pulls its temperature from a [`TemperatureProvider`]
+    /// instead of requiring one on every call to `measure`.
+    pub fn with_temperature_provider(
+        trigger: TRIGPIN,
+        echo: ECHOPIN,
+        config: Config,
+        temperature_provider: TP,
+    ) -> Self {
+        Self {
+            trigger,
+            echo,
+            config,
+            temperature_provider,
+            calibration_offset: 0.0,
+            cached_reading: None,
+        }
+    }
+
+    /// Set the per-sensor calibration offset (in the unit specified in the config) that is
+    /// subtracted from every computed distance.
+    pub fn set_calibration_offset(&mut self, offset: f64) {
+        self.calibration_offset = offset;
+    }
+
+    /// Calibrate against a reference target at a known distance. Takes `samples` measurements
+    /// (discarding timeouts and out-of-range readings, ignoring any previously set calibration
+    /// offset), stores the mean difference from `known_distance` as the new calibration offset,
+    /// and returns it. Leaves the previous offset in place if none of the samples succeeded.
+    ///
+    /// Note: exercising this method end-to-end needs a running async executor and time driver,
+    /// which this crate's test module doesn't set up; see `persist_and_load_calibration_round_trip`
+    /// for sync coverage of the calibration-offset plumbing.
+    pub async fn calibrate_against(
+        &mut self,
+        known_distance: f64,
+        temperature: f64,
+        samples: usize,
+    ) -> Result<f64, Hcsr04Error> {
+        let previous_offset = self.calibration_offset;
+        self.calibration_offset = 0.0;
+
+        let mut total_error = 0.0;
+        let mut valid_samples = 0usize;
+        for i in 0..samples {
+            if let Ok(Some(measured)) = self.measure(temperature).await {
+                total_error += measured - known_distance;
+                valid_samples += 1;
             }
-        };
-        331.5 * sqrt(1.0 + (temp/273.15))
+            if i + 1 < samples {
+                Timer::after(Duration::from_millis(60)).await;
+            }
+        }
+
+        if valid_samples == 0 {
+            self.calibration_offset = previous_offset;
+            return Err(Hcsr04Error::NoValidSamples);
+        }
+
+        let offset = total_error / valid_samples as f64;
+        self.calibration_offset = offset;
+        Ok(offset)
+    }
+
+    /// Persist the calibration offset to flash so it survives a reboot. `offset_addr` must be
+    /// aligned to `S::ERASE_SIZE`.
+    pub fn persist<S: embedded_storage::nor_flash::NorFlash>(
+        &self,
+        flash: &mut S,
+        offset_addr: u32,
+    ) -> Result<(), S::Error> {
+        let bytes = self.calibration_offset.to_le_bytes();
+        flash.erase(offset_addr, offset_addr + S::ERASE_SIZE as u32)?;
+        flash.write(offset_addr, &bytes)
+    }
+
+    /// Load a calibration offset previously written with `persist`.
+    pub fn load_calibration<S: embedded_storage::nor_flash::NorFlash>(
+        &mut self,
+        flash: &mut S,
+        offset_addr: u32,
+    ) -> Result<(), S::Error> {
+        let mut bytes = [0u8; 8];
+        flash.read(offset_addr, &mut bytes)?;
+        self.calibration_offset = f64::from_le_bytes(bytes);
+        Ok(())
+    }
+
+    /// Measure the distance in the unit specified in the config.
+    /// Queries the configured [`TemperatureProvider`] for the current temperature instead of
+    /// requiring the caller to supply one.
+    pub async fn measure_auto(&mut self) -> Result<Option<f64>, Hcsr04Error> {
+        let temperature = self.temperature_provider.temperature().await;
+        self.measure(temperature).await
+    }
+
+    /// Convert a temperature in the unit specified in the config to Celsius.
+    fn to_celsius(&self, temperature: f64) -> f64 {
+        match self.config.temperature_unit {
+            TemperatureUnit::Celsius => temperature,
+            TemperatureUnit::Fahrenheit => (temperature - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    /// Calculate the speed of sound in meters per second, adjusted for temperature and,
+    /// optionally, relative humidity.
+    /// Takes a temperature in units specified in the config and a relative humidity percentage
+    /// (0-100). When `humidity` is `None`, falls back to the temperature-only approximation.
+    fn speed_of_sound_adjusted(&self, temperature: f64, humidity: Option<f64>) -> f64 {
+        let temp_celsius = self.to_celsius(temperature);
+        match humidity {
+            Some(relative_humidity) => 331.3 + 0.606 * temp_celsius + 0.0124 * relative_humidity,
+            None => 331.5 * sqrt(1.0 + (temp_celsius / 273.15)),
+        }
     }
 
     /// Calculate the distance in centimeters based on the speed of sound and the duration of the pulse.
@@ -78,45 +268,157 @@ impl<TRIGPIN: OutputPin, ECHOPIN: InputPin + Wait> Hcsr04<TRIGPIN, ECHOPIN> {
     /// Returns the distance in the unit specified in the config.
     fn distance(&self, speed_of_sound: f64, duration_secs:f64) -> f64 {
         let distance = (speed_of_sound * 100.0 * duration_secs) / 2.0;
-        match self.config.distance_unit 
+        match self.config.distance_unit
         {
             DistanceUnit::Centimeters => distance,
             DistanceUnit::Inches => distance / 2.54,
         }
     }
 
+    /// Convert a distance in the unit specified in the config to meters.
+    fn to_meters(&self, distance: f64) -> f64 {
+        match self.config.distance_unit {
+            DistanceUnit::Centimeters => distance / 100.0,
+            DistanceUnit::Inches => distance * 2.54 / 100.0,
+        }
+    }
+
+    /// The maximum time to wait for the echo pin to go low before giving up on an object being
+    /// within `max_distance`, derived from `max_distance` and the current speed of sound.
+    fn echo_timeout(&self, speed_of_sound: f64) -> Duration {
+        let max_distance_m = self.to_meters(self.config.max_distance);
+        let timeout_secs = (2.0 * max_distance_m) / speed_of_sound;
+        Duration::from_micros((timeout_secs * 1_000_000.0) as u64)
+    }
+
     /// Measure the distance in the unit specified in the config.
     /// Takes a temperature in units specified in the config.
-    /// Returns the distance in the unit specified in the config.
-    pub async fn measure(&mut self, temperature: f64) -> Result<f64, &'static str> {
+    /// Returns `Ok(Some(distance))` in the unit specified in the config, or `Ok(None)` if no
+    /// echo was detected within `max_distance`.
+    ///
+    /// Uses the `humidity` configured in `Config`, if any, to refine the speed of sound. Use
+    /// `measure_with_humidity` to supply a one-off humidity reading instead.
+    pub async fn measure(&mut self, temperature: f64) -> Result<Option<f64>, Hcsr04Error> {
+        self.measure_core(temperature, self.config.humidity).await
+    }
+
+    /// Measure the distance in the unit specified in the config, compensating the speed of
+    /// sound for the given relative humidity (as a percentage, 0-100) in addition to
+    /// temperature.
+    pub async fn measure_with_humidity(
+        &mut self,
+        temperature: f64,
+        humidity: f64,
+    ) -> Result<Option<f64>, Hcsr04Error> {
+        self.measure_core(temperature, Some(humidity)).await
+    }
+
+    /// Measure the distance, but reuse the last reading if it is younger than `max_age` instead
+    /// of pulsing the trigger again. This lets several callers share one sensor without
+    /// re-triggering it on every request. A transient error is never cached, so the next call
+    /// retries the sensor instead of repeating the failure.
+    ///
+    /// Note: the age gate is built on `embassy_time::Instant::now()`, which panics without a
+    /// registered time driver, so this crate's test module (which runs with none) can't exercise
+    /// the cache-hit/expiry paths directly.
+    pub async fn measure_cached(
+        &mut self,
+        temperature: f64,
+        max_age: Duration,
+    ) -> Result<Option<f64>, Hcsr04Error> {
+        if let Some((reading, taken_at)) = self.cached_reading {
+            if Instant::now() - taken_at < max_age {
+                return Ok(reading);
+            }
+        }
+
+        let reading = self.measure(temperature).await?;
+        self.cached_reading = Some((reading, Instant::now()));
+        Ok(reading)
+    }
+
+    async fn measure_core(&mut self, temperature: f64, humidity: Option<f64>) -> Result<Option<f64>, Hcsr04Error> {
         // error if the echo pin is already high
-        if self.echo.is_high().ok().unwrap() {
-            return Err("Echo pin is already high");
+        if self.echo.is_high().map_err(|_| Hcsr04Error::EchoPin)? {
+            return Err(Hcsr04Error::EchoAlreadyHigh);
         }
 
         // Send a 10us pulse to the trigger pin
-        self.trigger.set_high().ok();
+        self.trigger.set_high().map_err(|_| Hcsr04Error::TriggerPin)?;
         Timer::after(Duration::from_micros(10)).await;
-        self.trigger.set_low().ok();
+        self.trigger.set_low().map_err(|_| Hcsr04Error::TriggerPin)?;
 
         // Wait for the echo pin to go high with a timeout. If the timeout is reached, return an error.
         let start = match with_timeout(Duration::from_secs(2), self.echo.wait_for_high()).await {
-            Ok(_) => Instant::now(),
-            Err(_) => return Err("Timeout waiting for echo pin to go high"),
+            Ok(Ok(_)) => Instant::now(),
+            Ok(Err(_)) => return Err(Hcsr04Error::EchoPin),
+            Err(_) => return Err(Hcsr04Error::EchoTimeoutHigh),
         };
 
-        // Wait for the echo pin to go low with a timeout. If the timeout is reached, return an error.
-        let end = match with_timeout(Duration::from_secs(2), self.echo.wait_for_low()).await {
-            Ok(_) => Instant::now(),
-            Err(_) => return Err("Timeout waiting for echo pin to go low"),
+        let speed_of_sound = self.speed_of_sound_adjusted(temperature, humidity);
+
+        // Wait for the echo pin to go low, but no longer than it would take an echo from
+        // max_distance away to return. If that window elapses, the object is out of range.
+        let end = match with_timeout(self.echo_timeout(speed_of_sound), self.echo.wait_for_low()).await {
+            Ok(Ok(_)) => Instant::now(),
+            Ok(Err(_)) => return Err(Hcsr04Error::EchoTimeoutLow),
+            Err(_) => return Ok(None),
         };
 
-        // Calculate the distance
+        // Calculate the distance, subtracting any configured calibration offset
         let pulse_duration_secs = (end - start).as_micros() as f64 / 1_000_000.0;
-        Ok(self.distance(
-            self.speed_of_sound_temperature_adjusted(temperature),
-            pulse_duration_secs,
-        ))
+        let distance = self.distance(speed_of_sound, pulse_duration_secs) - self.calibration_offset;
+        Ok(Some(distance))
+    }
+
+    /// Take up to `samples` measurements, discarding timeouts and out-of-range readings, and
+    /// combine the valid ones according to `strategy`. Waits 60 ms between samples to let echoes
+    /// decay, and returns `Hcsr04Error::NoValidSamples` if none of the samples succeeded.
+    ///
+    /// `samples` is capped at `MAX_FILTER_SAMPLES` since readings are collected into a
+    /// fixed-capacity, allocation-free buffer.
+    pub async fn measure_filtered(
+        &mut self,
+        temperature: f64,
+        samples: usize,
+        strategy: FilterStrategy,
+    ) -> Result<f64, Hcsr04Error> {
+        let sample_count = samples.min(MAX_FILTER_SAMPLES);
+        let mut readings: Vec<f64, MAX_FILTER_SAMPLES> = Vec::new();
+
+        for i in 0..sample_count {
+            if let Ok(Some(distance)) = self.measure(temperature).await {
+                let _ = readings.push(distance);
+            }
+            if i + 1 < sample_count {
+                Timer::after(Duration::from_millis(60)).await;
+            }
+        }
+
+        if readings.is_empty() {
+            return Err(Hcsr04Error::NoValidSamples);
+        }
+
+        Ok(Self::combine_readings(&mut readings, strategy))
+    }
+
+    /// Combine a non-empty slice of valid readings into a single distance according to
+    /// `strategy`. Split out of `measure_filtered` so the combination math can be tested without
+    /// driving an actual measurement.
+    fn combine_readings(readings: &mut [f64], strategy: FilterStrategy) -> f64 {
+        match strategy {
+            FilterStrategy::Mean => readings.iter().sum::<f64>() / readings.len() as f64,
+            FilterStrategy::MinValid => readings.iter().copied().fold(f64::INFINITY, f64::min),
+            FilterStrategy::Median => {
+                readings.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+                let mid = readings.len() / 2;
+                if readings.len().is_multiple_of(2) {
+                    (readings[mid - 1] + readings[mid]) / 2.0
+                } else {
+                    readings[mid]
+                }
+            }
+        }
     }
 }
 
@@ -195,14 +497,62 @@ mod tests {
         }
     }
 
+    struct MockFlash {
+        data: [u8; 64],
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            Self { data: [0xFF; 64] }
+        }
+    }
+
+    impl embedded_storage::nor_flash::ErrorType for MockFlash {
+        type Error = embedded_storage::nor_flash::NorFlashErrorKind;
+    }
+
+    impl embedded_storage::nor_flash::ReadNorFlash for MockFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl embedded_storage::nor_flash::NorFlash for MockFlash {
+        const WRITE_SIZE: usize = 1;
+        const ERASE_SIZE: usize = 64;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            for byte in &mut self.data[from as usize..to as usize] {
+                *byte = 0xFF;
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
     #[test]
     fn speevd_of_sound_m_per_s_temperature_adjusted_0() {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
-        assert_eq!(round(sensor.speed_of_sound_temperature_adjusted(0.0)), round(331.5));
+        assert_eq!(round(sensor.speed_of_sound_adjusted(0.0, None)), round(331.5));
     }
 
     #[test]
@@ -210,9 +560,11 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
-        assert_eq!(round(sensor.speed_of_sound_temperature_adjusted(20.0)), round(343.42));
+        assert_eq!(round(sensor.speed_of_sound_adjusted(20.0, None)), round(343.42));
     }
 
     #[test]
@@ -220,9 +572,11 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
-        assert_eq!(round(sensor.speed_of_sound_temperature_adjusted(40.0)), round(354.94));
+        assert_eq!(round(sensor.speed_of_sound_adjusted(40.0, None)), round(354.94));
     }
 
     #[test]
@@ -230,6 +584,8 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
         assert_eq!(sensor.distance(343.14, 0.0), 0.0);
@@ -240,6 +596,8 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
         assert_eq!(sensor.distance(343.14, 0.005), 85.785);
@@ -250,6 +608,8 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
         assert_eq!(sensor.distance(343.14, 0.01), 171.57);
@@ -260,9 +620,11 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Centimeters,
             temperature_unit: TemperatureUnit::Fahrenheit,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
-        assert_eq!(round(sensor.speed_of_sound_temperature_adjusted(32.0)), round(331.5));
+        assert_eq!(round(sensor.speed_of_sound_adjusted(32.0, None)), round(331.5));
     }
 
     #[test]
@@ -270,6 +632,8 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Inches,
             temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
         assert_eq!(round(sensor.distance(343.14, 0.01)), round(67.56));
@@ -280,9 +644,127 @@ mod tests {
         let config = Config {
             distance_unit: DistanceUnit::Inches,
             temperature_unit: TemperatureUnit::Fahrenheit,
+            humidity: None,
+            max_distance: 400.0,
         };
         let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
-        assert_eq!(round(sensor.speed_of_sound_temperature_adjusted(32.0)), round(331.5));
+        assert_eq!(round(sensor.speed_of_sound_adjusted(32.0, None)), round(331.5));
         assert_eq!(round(sensor.distance(343.14, 0.01)), round(67.56));
     }
+
+    #[test]
+    fn speed_of_sound_m_per_s_humidity_adjusted_20_50() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        assert_eq!(round(sensor.speed_of_sound_adjusted(20.0, Some(50.0))), round(344.04));
+    }
+
+    #[test]
+    fn speed_of_sound_falls_back_without_humidity() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        assert_eq!(round(sensor.speed_of_sound_adjusted(20.0, None)), round(343.42));
+    }
+
+    #[test]
+    fn echo_timeout_scales_with_max_distance() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        // 4 m there and back at roughly 343 m/s is about 23.3 ms.
+        assert_eq!(round(sensor.echo_timeout(343.0).as_micros() as f64 / 1000.0), round(23.32));
+    }
+
+    #[test]
+    fn to_celsius_converts_fahrenheit() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        assert_eq!(sensor.to_celsius(32.0), 0.0);
+    }
+
+    #[test]
+    fn constant_temperature_stores_value() {
+        assert_eq!(ConstantTemperature(23.5).0, 23.5);
+    }
+
+    #[test]
+    fn new_defaults_constant_temperature_to_configured_unit() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        assert_eq!(sensor.temperature_provider.0, 68.0);
+    }
+
+    #[test]
+    fn combine_readings_median_odd_count() {
+        let mut readings = [30.0, 10.0, 20.0];
+        let result =
+            Hcsr04::<OutputPinMock, InputPinMock>::combine_readings(&mut readings, FilterStrategy::Median);
+        assert_eq!(result, 20.0);
+    }
+
+    #[test]
+    fn combine_readings_median_even_count_averages_middle_two() {
+        let mut readings = [10.0, 40.0, 20.0, 30.0];
+        let result =
+            Hcsr04::<OutputPinMock, InputPinMock>::combine_readings(&mut readings, FilterStrategy::Median);
+        assert_eq!(result, 25.0);
+    }
+
+    #[test]
+    fn combine_readings_mean() {
+        let mut readings = [10.0, 20.0, 30.0];
+        let result =
+            Hcsr04::<OutputPinMock, InputPinMock>::combine_readings(&mut readings, FilterStrategy::Mean);
+        assert_eq!(result, 20.0);
+    }
+
+    #[test]
+    fn persist_and_load_calibration_round_trip() {
+        let config = Config {
+            distance_unit: DistanceUnit::Centimeters,
+            temperature_unit: TemperatureUnit::Celsius,
+            humidity: None,
+            max_distance: 400.0,
+        };
+        let mut sensor = Hcsr04::new(OutputPinMock, InputPinMock, config);
+        sensor.set_calibration_offset(1.25);
+        let mut flash = MockFlash::new();
+        sensor.persist(&mut flash, 0).unwrap();
+
+        sensor.set_calibration_offset(0.0);
+        sensor.load_calibration(&mut flash, 0).unwrap();
+        assert_eq!(sensor.calibration_offset, 1.25);
+    }
+
+    #[test]
+    fn combine_readings_min_valid() {
+        let mut readings = [30.0, 10.0, 20.0];
+        let result =
+            Hcsr04::<OutputPinMock, InputPinMock>::combine_readings(&mut readings, FilterStrategy::MinValid);
+        assert_eq!(result, 10.0);
+    }
 }